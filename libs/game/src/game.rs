@@ -1,6 +1,8 @@
 use features::{log, GLOBAL_ERROR_LOGGER, GLOBAL_LOGGER};
 use platform_types::{Button, Input, Speaker, State, StateParams, SFX};
-use rendering::{Framebuffer, BLUE, GREEN, PALETTE, PURPLE, RED, WHITE, YELLOW};
+use rendering::{
+    Framebuffer, BLUE, GREEN, PALETTE, PURPLE, RED, SCREEN_HEIGHT, SCREEN_WIDTH, WHITE, YELLOW,
+};
 
 const GRID_WIDTH: u8 = 40;
 const GRID_HEIGHT: u8 = 60;
@@ -27,6 +29,40 @@ fn get_colours(mut spec: HalfHexSpec) -> (u32, u32) {
     )
 }
 
+//Frames a direction must be held before auto-repeat kicks in, then the gap
+//between repeats once it does.
+const REPEAT_DELAY: usize = 12;
+const REPEAT_INTERVAL: usize = 4;
+
+//Edge/held view over a frame's gamepad state, derived from the current and
+//previous `Input` bitsets. Replaces the ad-hoc `pressed_this_frame` checks.
+struct InputState {
+    gamepad: Button::Ty,
+    previous: Button::Ty,
+}
+
+impl InputState {
+    fn new(input: Input) -> InputState {
+        InputState {
+            gamepad: input.gamepad,
+            previous: input.previous_gamepad,
+        }
+    }
+
+    fn just_pressed(&self, button: Button::Ty) -> bool {
+        self.gamepad.contains(button) && !self.previous.contains(button)
+    }
+
+    #[allow(dead_code)]
+    fn just_released(&self, button: Button::Ty) -> bool {
+        !self.gamepad.contains(button) && self.previous.contains(button)
+    }
+
+    fn held(&self, button: Button::Ty) -> bool {
+        self.gamepad.contains(button)
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Cursor {
     Unselected(usize),
@@ -73,6 +109,8 @@ struct Animation {
     x_rate: u8,
     y_rate: u8,
     spec: HalfHexSpec,
+    //False for the zero-length refill placeholders, so they make no landing sound.
+    moves: bool,
 }
 
 use std::cmp::{max, min};
@@ -109,6 +147,7 @@ impl Animation {
             target_x,
             target_y,
             spec,
+            moves: x != target_x || y != target_y,
         }
     }
 
@@ -155,11 +194,120 @@ impl Animation {
     }
 }
 
+//The smallest connected run of equal-coloured cells that clears.
+const MIN_MATCH: usize = 3;
+
+//Length of the per-scanline cosine table and the peak horizontal offset, in
+//pixels, of the wobble post-effect.
+const WOBBLE_LEN: usize = 64;
+const WOBBLE_AMPLITUDE: f32 = 3.0;
+
+//How many frames the wobble lingers after a big cascade triggers it.
+const WOBBLE_DURATION: usize = 48;
+
+//Build the cosine lookup table once; values are the signed pixel offset for
+//each phase step.
+fn wobble_table() -> [i8; WOBBLE_LEN] {
+    let mut table = [0i8; WOBBLE_LEN];
+    for (i, slot) in table.iter_mut().enumerate() {
+        let theta = (i as f32) * std::f32::consts::TAU / (WOBBLE_LEN as f32);
+        *slot = (theta.cos() * WOBBLE_AMPLITUDE) as i8;
+    }
+    table
+}
+
+//A tiny xorshift PRNG seeded from the `StateParams` seed so the falling board
+//stays deterministic without pulling in an external rng crate.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: [u8; 16]) -> Rng {
+        let mut state = 0u64;
+        for (i, byte) in seed.iter().enumerate() {
+            state ^= (*byte as u64) << ((i & 7) * 8);
+        }
+        //Avoid the all-zero state, which xorshift cannot escape.
+        Rng {
+            state: state | 1,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    //A fresh half-hex spec with random inside/outline palette indices.
+    fn spec(&mut self) -> HalfHexSpec {
+        let inside = (self.next_u64() & 0b111) as u8;
+        let outline = (self.next_u64() & 0b111) as u8;
+        inside | (outline << 4)
+    }
+}
+
 pub struct GameState {
     grid: Grid,
     cursor: Cursor,
     frame_counter: usize,
     animations: Vec<Animation>,
+    min_match: usize,
+    //Cumulative clearing depth across a whole settle chain (one player swap): bumped
+    //once per settle that clears, driven by the gravity chain re-running `settle()`.
+    //Scales SFX pitch and gates the wobble flourish; reset once the board rests.
+    combo: usize,
+    rng: Rng,
+    //Next frame each direction (Up/Down/Left/Right) may fire while held.
+    repeat_timers: [usize; 4],
+    //Per-scanline wobble post-effect: precomputed table, a persistent toggle,
+    //and a countdown used for automatic flourishes.
+    wobble: [i8; WOBBLE_LEN],
+    wobble_enabled: bool,
+    wobble_ticks: usize,
+}
+
+//A single authored cell placement in a `Level`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Placement {
+    pub x: u8,
+    pub y: u8,
+    pub spec: HalfHexSpec,
+}
+
+//A designed puzzle, authored as JSON5 and turned into a board by
+//`GameState::from_level`; `EntireState::new` does not load one and still starts
+//from the seeded demo grid. Mirrors how the rest of the data-driven layouts are
+//described as text.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Level {
+    pub placements: Vec<Placement>,
+    #[serde(default = "default_min_match")]
+    pub min_match: usize,
+    //Optional per-colour goals, keyed by inside palette index.
+    #[serde(default)]
+    pub goals: Vec<ColorGoal>,
+}
+
+//How many cells of a given colour a level wants cleared.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ColorGoal {
+    pub colour: u8,
+    pub count: u32,
+}
+
+fn default_min_match() -> usize {
+    MIN_MATCH
+}
+
+//A level that could not be turned into a board.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LevelError {
+    OutOfRange { x: u8, y: u8 },
 }
 
 fn new_grid() -> Grid {
@@ -173,7 +321,7 @@ fn new_grid() -> Grid {
 }
 
 impl GameState {
-    pub fn new(_seed: [u8; 16]) -> GameState {
+    pub fn new(seed: [u8; 16]) -> GameState {
         let grid: Grid = new_grid();
 
         GameState {
@@ -181,8 +329,287 @@ impl GameState {
             cursor: Cursor::Unselected(GRID_WIDTH as usize + 1),
             frame_counter: 0,
             animations: Vec::with_capacity(8),
+            min_match: MIN_MATCH,
+            combo: 0,
+            rng: Rng::new(seed),
+            repeat_timers: [0; 4],
+            wobble: wobble_table(),
+            wobble_enabled: false,
+            wobble_ticks: 0,
+        }
+    }
+
+    //Resolve a held direction into a fire-this-frame flag: true on the initial
+    //press, then again every `REPEAT_INTERVAL` frames after `REPEAT_DELAY`.
+    fn repeat(&mut self, input: &InputState, button: Button::Ty, dir: Dir) -> bool {
+        let slot = dir as usize;
+        let frame = self.frame_counter;
+
+        if input.just_pressed(button) {
+            self.repeat_timers[slot] = frame + REPEAT_DELAY;
+            true
+        } else if input.held(button) && frame >= self.repeat_timers[slot] {
+            self.repeat_timers[slot] = frame + REPEAT_INTERVAL;
+            true
+        } else {
+            false
+        }
+    }
+
+    //Build a board from an authored level, placing each cell and honouring the
+    //level's `min_match`. The PRNG used for refills is seeded deterministically.
+    //Coordinates come straight from a text file, so out-of-range placements are
+    //rejected rather than indexed into the `grid`.
+    pub fn from_level(level: &Level) -> Result<GameState, LevelError> {
+        let mut grid: Grid = [None; GRID_LENGTH];
+        for placement in &level.placements {
+            if placement.x >= GRID_WIDTH || placement.y >= GRID_HEIGHT {
+                return Err(LevelError::OutOfRange {
+                    x: placement.x,
+                    y: placement.y,
+                });
+            }
+            grid[xy_to_i(placement.x, placement.y)] = Some(placement.spec);
+        }
+
+        Ok(GameState {
+            grid,
+            cursor: Cursor::Unselected(GRID_WIDTH as usize + 1),
+            frame_counter: 0,
+            animations: Vec::with_capacity(8),
+            min_match: level.min_match,
+            combo: 0,
+            rng: Rng::new([0; 16]),
+            repeat_timers: [0; 4],
+            wobble: wobble_table(),
+            wobble_enabled: false,
+            wobble_ticks: 0,
+        })
+    }
+
+    //Flip the persistent wobble toggle, e.g. for a scene transition.
+    pub fn toggle_wobble(&mut self) {
+        self.wobble_enabled = !self.wobble_enabled;
+    }
+
+    //Whether the wobble effect should be applied this frame.
+    fn wobble_active(&self) -> bool {
+        self.wobble_enabled || self.wobble_ticks > 0
+    }
+
+    //Resolve a settled board: clear matching groups, then let survivors fall and
+    //refill the vacated top cells. Gravity/refill spawn `Animation`s, so the
+    //cascade loop re-runs for free when those settle.
+    //Returns the number of groups cleared during this settle so callers can emit
+    //one SFX per group.
+    fn settle(&mut self) -> usize {
+        let groups = self.resolve_matches();
+        //A big combo — a cascade several clears deep via the gravity chain — kicks
+        //off a brief wobble flourish.
+        if self.combo >= 3 {
+            self.wobble_ticks = WOBBLE_DURATION;
+        }
+        self.apply_gravity();
+        //If nothing cleared and the board has no more motion queued, the chain
+        //started by the player's swap is over, so the combo resets.
+        if groups == 0 && self.animations.is_empty() {
+            self.combo = 0;
+        }
+        groups
+    }
+
+    //Move each surviving spec one step into the empty cell below it via the
+    //`Dir::Down` offset, spawning an `Animation` for the motion.
+    //
+    //Caveat: `Dir::Down` is a diagonal hex step, so in the two edge columns it
+    //wraps on some row types (the first column when `y % ROW_TYPES == 0`, the last
+    //when `y % ROW_TYPES == 2`) and `offset_index` rejects it — pieces in those
+    //cells cannot fall. Everywhere else pieces drain by falling, one step per settle.
+    fn apply_gravity(&mut self) {
+        let mut reserved = [false; GRID_LENGTH];
+        let mut moved = false;
+
+        //Bottom-up so a cell only moves once per pass; the next settle continues
+        //the fall, letting pieces drop an arbitrary distance over several steps.
+        for y in (0..GRID_HEIGHT).rev() {
+            for x in 0..GRID_WIDTH {
+                let i = xy_to_i(x, y);
+
+                if let Some(spec) = self.grid[i] {
+                    if let Some(down) = offset_index(i, x, y, Dir::Down) {
+                        if self.grid[down].is_none() && !reserved[down] {
+                            self.grid[i] = None;
+                            reserved[down] = true;
+                            self.animations.push(Animation::new(i, down, spec));
+                            moved = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if moved {
+            //Let the falling pieces settle before refilling above them.
+            return;
+        }
+
+        //`moved == false` means no filled cell has an empty `Down` target, so no
+        //remaining hole has a piece that can fall into it: it is a cell no `Down`
+        //step lands on (the top row, edge-column gaps, cells the two-row `2W±1`
+        //offsets skip over) or it lies below such a hole. Every hole is refilled
+        //here at once, so no pending drop is pre-empted.
+        for i in 0..GRID_LENGTH {
+            if self.grid[i].is_none() {
+                let spec = self.rng.spec();
+                //A zero-length animation threads the new spec through the same
+                //settle pipeline so it can immediately take part in a cascade.
+                self.animations.push(Animation::new(i, i, spec));
+            }
         }
     }
+
+    //Clear every connected group of same-coloured cells of at least `min_match`.
+    //A single pass already clears *all* such groups across the board, and setting
+    //cells to `None` can never join two survivors into a new group, so there is no
+    //point re-scanning here. Real chain reactions come from gravity dropping pieces
+    //into new adjacencies and `settle()` running again — that depth is tracked in
+    //`combo`, which this bumps once per settle that actually clears something.
+    //Returns how many groups were cleared.
+    fn resolve_matches(&mut self) -> usize {
+        let groups = self.clear_matching_groups();
+        if groups > 0 {
+            self.combo += 1;
+        }
+        groups
+    }
+
+    //A single flood-fill pass. Returns how many distinct groups were cleared.
+    fn clear_matching_groups(&mut self) -> usize {
+        let mut visited = [false; GRID_LENGTH];
+        let mut to_clear = [false; GRID_LENGTH];
+
+        let mut group = Vec::new();
+        let mut stack = Vec::new();
+        let mut groups = 0;
+
+        for start in 0..GRID_LENGTH {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+
+            let colours = match self.grid[start] {
+                Some(spec) => get_colours(spec),
+                None => continue,
+            };
+
+            group.clear();
+            stack.clear();
+            stack.push(start);
+
+            while let Some(i) = stack.pop() {
+                group.push(i);
+
+                for neighbour in neighbours(i).into_iter().flatten() {
+                    if visited[neighbour] {
+                        continue;
+                    }
+                    if self.grid[neighbour].map(get_colours) == Some(colours) {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+
+            if group.len() >= self.min_match {
+                groups += 1;
+                for &i in &group {
+                    to_clear[i] = true;
+                }
+            }
+        }
+
+        for (i, clear) in to_clear.iter().enumerate() {
+            if *clear {
+                self.grid[i] = None;
+            }
+        }
+        groups
+    }
+}
+
+//The four hex neighbours of `i`, respecting the hex topology via the movement
+//table rather than raw ±1 offsets. `None` where the move leaves the grid or
+//wraps across an edge.
+fn neighbours(i: usize) -> [Option<usize>; 4] {
+    let (x, y) = i_to_xy(i);
+    [
+        offset_index(i, x, y, Dir::Up),
+        offset_index(i, x, y, Dir::Down),
+        offset_index(i, x, y, Dir::Left),
+        offset_index(i, x, y, Dir::Right),
+    ]
+}
+
+//Apply a single `get_movement_offset` step to `i`, rejecting moves that leave
+//the grid or wrap around a horizontal edge (mirrors the `move_hex!` bounds).
+fn offset_index(i: usize, x: u8, y: u8, dir: Dir) -> Option<usize> {
+    let offset = get_movement_offset(x, y, dir);
+    let new_i = i.wrapping_add(offset as usize);
+
+    if new_i >= GRID_LENGTH {
+        return None;
+    }
+
+    let width = GRID_WIDTH as usize;
+    let new_x = new_i % width;
+    let old_x = x as usize;
+    let looped = (old_x == 0 && new_x == width - 1) || (old_x == width - 1 && new_x == 0);
+
+    if looped {
+        None
+    } else {
+        Some(new_i)
+    }
+}
+
+//The button chord that toggles GIF recording when held. A function rather than a
+//`const` because the bitflag `BitOr` is not usable in const context.
+fn record_chord() -> Button::Ty {
+    Button::Select | Button::B
+}
+
+//Captured frames are stored as palette indices, which is exactly what an
+//indexed GIF wants, so recording stays cheap.
+struct Recorder {
+    recording: bool,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recorder {
+    fn new() -> Recorder {
+        Recorder {
+            recording: false,
+            frames: Vec::new(),
+        }
+    }
+
+    //Map a rendered `u32` pixel back to its fixed-`PALETTE` index, falling back
+    //to 0 for anything off-palette.
+    fn palette_index(pixel: u32) -> u8 {
+        PALETTE
+            .iter()
+            .position(|&c| c == pixel)
+            .unwrap_or(0) as u8
+    }
+
+    fn capture(&mut self, buffer: &[u32]) {
+        if !self.recording {
+            return;
+        }
+        self.frames
+            .push(buffer.iter().map(|&p| Self::palette_index(p)).collect());
+    }
 }
 
 pub struct EntireState {
@@ -190,6 +617,7 @@ pub struct EntireState {
     pub framebuffer: Framebuffer,
     pub input: Input,
     pub speaker: Speaker,
+    recorder: Recorder,
 }
 
 impl EntireState {
@@ -206,7 +634,43 @@ impl EntireState {
             framebuffer,
             input: Input::new(),
             speaker: Speaker::new(),
+            recorder: Recorder::new(),
+        }
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recorder.frames.clear();
+        self.recorder.recording = true;
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder.recording = false;
+    }
+
+    //Encode the captured frames as an animated GIF whose global colour table is
+    //built directly from the fixed `PALETTE`.
+    pub fn write_gif<W: std::io::Write>(&self, writer: W) -> Result<(), gif::EncodingError> {
+        let mut table = Vec::with_capacity(PALETTE.len() * 3);
+        for &colour in PALETTE.iter() {
+            table.push((colour >> 16) as u8);
+            table.push((colour >> 8) as u8);
+            table.push(colour as u8);
+        }
+
+        let mut encoder =
+            gif::Encoder::new(writer, SCREEN_WIDTH as u16, SCREEN_HEIGHT as u16, &table)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        for indices in &self.recorder.frames {
+            let mut frame = gif::Frame::default();
+            frame.width = SCREEN_WIDTH as u16;
+            frame.height = SCREEN_HEIGHT as u16;
+            frame.delay = 2; //hundredths of a second, roughly 50fps playback.
+            frame.buffer = std::borrow::Cow::Borrowed(indices);
+            encoder.write_frame(&frame)?;
         }
+
+        Ok(())
     }
 }
 
@@ -219,6 +683,18 @@ impl State for EntireState {
             &mut self.speaker,
         );
 
+        //Toggle recording on the rising edge of the record chord.
+        let chord_held = self.input.gamepad.contains(record_chord());
+        let chord_was_held = self.input.previous_gamepad.contains(record_chord());
+        if chord_held && !chord_was_held {
+            if self.recorder.recording {
+                self.stop_recording();
+            } else {
+                self.start_recording();
+            }
+        }
+        self.recorder.capture(&self.framebuffer.buffer);
+
         self.input.previous_gamepad = self.input.gamepad;
 
         for request in self.speaker.drain() {
@@ -227,12 +703,8 @@ impl State for EntireState {
     }
 
     fn press(&mut self, button: Button::Ty) {
-        if self.input.previous_gamepad.contains(button) {
-            //This is meant to pass along the key repeat, if any.
-            //Not sure if rewriting history is the best way to do this.
-            self.input.previous_gamepad.remove(button);
-        }
-
+        //Edges and key repeat are tracked explicitly in `InputState`/`GameState`,
+        //so we just record the button as down here.
         self.input.gamepad.insert(button);
     }
 
@@ -330,6 +802,16 @@ enum Dir {
     Right,
 }
 
+//Pick a clear SFX variant scaled by how deep the cascade is, so longer combos
+//sound bigger.
+fn clear_sfx(depth: usize) -> SFX {
+    match depth {
+        0 | 1 => SFX::ClearSmall,
+        2 => SFX::ClearMedium,
+        _ => SFX::ClearBig,
+    }
+}
+
 fn get_movement_offset(x: u8, y: u8, dir: Dir) -> i8 {
     let index = ((y % ROW_TYPES) << 3) | (on_left!(x, bit) << 2) | dir as u8;
 
@@ -363,34 +845,45 @@ pub fn update_and_render(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
-    _speaker: &mut Speaker,
+    speaker: &mut Speaker,
 ) {
     //
     //UPDATE
     //
+    let had_animations = !state.animations.is_empty();
+
     for animation_index in (0..state.animations.len()).rev() {
         let animation = &mut state.animations[animation_index];
         animation.approach_target();
 
         if animation.is_complete() {
             let index = xy_to_i(animation.x, animation.y);
+            let moved = animation.moves;
 
             state.grid[index] = Some(animation.spec);
 
-            let other_index = if on_left!(animation.x) {
-                index + 1
-            } else {
-                index - 1
-            };
-            if state.grid[other_index].map(get_colours) == state.grid[index].map(get_colours) {
-                state.grid[other_index] = None;
-                state.grid[index] = None;
+            state.animations.swap_remove(animation_index);
+            //Only a piece that actually travelled lands — the zero-length refill
+            //placeholders would otherwise enqueue a `Land` per refilled cell.
+            if moved {
+                speaker.request_sfx(SFX::Land);
             }
+        }
+    }
 
-            state.animations.swap_remove(animation_index);
+    //Once a swap has finished settling, run clearing, then gravity and refill.
+    if had_animations && state.animations.is_empty() {
+        let groups = state.settle();
+
+        //One cue per cleared group, pitched by the combo depth accumulated across
+        //the whole settle chain so longer combos sound bigger.
+        for _ in 0..groups {
+            speaker.request_sfx(clear_sfx(state.combo));
         }
     }
 
+    let input_state = InputState::new(input);
+
     match input.gamepad {
         Button::B => framebuffer.clear_to(BLUE),
         Button::Select => framebuffer.clear_to(WHITE),
@@ -398,11 +891,12 @@ pub fn update_and_render(
         _ => {}
     }
 
-    if input.pressed_this_frame(Button::A) {
+    if input_state.just_pressed(Button::A) {
         match state.cursor {
             Cursor::Unselected(c) => {
                 if state.grid[c].is_some() {
                     state.cursor = Cursor::Selected(c, c);
+                    speaker.request_sfx(SFX::Select);
                 }
             }
             Cursor::Selected(c1, c2) => {
@@ -412,6 +906,7 @@ pub fn update_and_render(
                     state.animations.push(Animation::new(c1, c2, h1));
                     state.animations.push(Animation::new(c2, c1, h2));
                     state.cursor = Cursor::Unselected(c2);
+                    speaker.request_sfx(SFX::Swap);
                 }
             }
         };
@@ -435,21 +930,22 @@ pub fn update_and_render(
                     (x == 0 && new_x == width - 1) || (x as usize == width - 1 && new_x == 0);
                 if !looped {
                     state.cursor = new_cursor;
+                    speaker.request_sfx(SFX::CursorMove);
                 }
             }
         };
     }
 
-    if input.pressed_this_frame(Button::Up) {
+    if state.repeat(&input_state, Button::Up, Dir::Up) {
         move_hex!(Dir::Up);
     }
-    if input.pressed_this_frame(Button::Down) {
+    if state.repeat(&input_state, Button::Down, Dir::Down) {
         move_hex!(Dir::Down);
     }
-    if input.pressed_this_frame(Button::Left) {
+    if state.repeat(&input_state, Button::Left, Dir::Left) {
         move_hex!(Dir::Left);
     }
-    if input.pressed_this_frame(Button::Right) {
+    if state.repeat(&input_state, Button::Right, Dir::Right) {
         move_hex!(Dir::Right);
     }
 
@@ -483,5 +979,190 @@ pub fn update_and_render(
         draw_hexagon(framebuffer, x, y, spec);
     }
 
+    //Per-scanline sine distortion, applied last so it wobbles the finished image.
+    if state.wobble_active() {
+        let phase = state.frame_counter;
+        for (y, row) in framebuffer
+            .buffer
+            .chunks_exact_mut(SCREEN_WIDTH)
+            .enumerate()
+        {
+            let offset = state.wobble[(y + phase) % WOBBLE_LEN];
+            if offset > 0 {
+                row.rotate_right(offset as usize);
+            } else if offset < 0 {
+                row.rotate_left(offset.unsigned_abs() as usize);
+            }
+        }
+
+        state.wobble_ticks = state.wobble_ticks.saturating_sub(1);
+    }
+
     state.frame_counter += 1;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        // a handful of placed cells
+        placements: [
+            { x: 0, y: 0, spec: 0x01 },
+            { x: 1, y: 0, spec: 0x12 },
+            { x: 0, y: 1, spec: 0x01 },
+        ],
+        min_match: 4,
+        goals: [ { colour: 1, count: 3 } ],
+    }"#;
+
+    #[test]
+    fn level_round_trips_from_json5() {
+        let level: Level = json5::from_str(SAMPLE).unwrap();
+
+        assert_eq!(level.placements.len(), 3);
+        assert_eq!(level.min_match, 4);
+        assert_eq!(level.goals.len(), 1);
+    }
+
+    #[test]
+    fn from_level_populates_the_grid() {
+        let level: Level = json5::from_str(SAMPLE).unwrap();
+        let state = GameState::from_level(&level).unwrap();
+
+        assert_eq!(state.grid[xy_to_i(0, 0)], Some(0x01));
+        assert_eq!(state.grid[xy_to_i(1, 0)], Some(0x12));
+        assert_eq!(state.grid[xy_to_i(0, 1)], Some(0x01));
+        assert_eq!(state.grid[xy_to_i(5, 5)], None);
+        assert_eq!(state.min_match, 4);
+    }
+
+    #[test]
+    fn from_level_rejects_out_of_range_placements() {
+        let level: Level =
+            json5::from_str("{ placements: [ { x: 50, y: 0, spec: 0x01 } ] }").unwrap();
+
+        assert_eq!(
+            GameState::from_level(&level).err(),
+            Some(LevelError::OutOfRange { x: 50, y: 0 })
+        );
+    }
+
+    #[test]
+    fn min_match_defaults_when_omitted() {
+        let level: Level = json5::from_str("{ placements: [] }").unwrap();
+        assert_eq!(level.min_match, MIN_MATCH);
+    }
+
+    //Complete any queued animations in place (skipping the frame-by-frame
+    //approach) and re-`settle`, the way `update_and_render` does, until the board
+    //comes to rest. Returns the number of settles that ran.
+    fn run_to_rest(state: &mut GameState) -> usize {
+        let mut settles = 0;
+        while !state.animations.is_empty() {
+            for animation in std::mem::take(&mut state.animations) {
+                state.grid[xy_to_i(animation.target_x, animation.target_y)] =
+                    Some(animation.spec);
+            }
+            state.settle();
+            settles += 1;
+            assert!(settles < 10_000, "settle did not converge");
+        }
+        settles
+    }
+
+    #[test]
+    fn connected_group_clears_and_deepens_combo() {
+        let mut state = GameState::new([0; 16]);
+        state.grid = [None; GRID_LENGTH];
+
+        //A start cell plus `min_match - 1` of its neighbours form one connected
+        //group of equal colour.
+        let start = xy_to_i(10, 10);
+        let mut cells = vec![start];
+        for neighbour in neighbours(start).into_iter().flatten() {
+            if cells.len() < state.min_match {
+                cells.push(neighbour);
+            }
+        }
+        assert_eq!(cells.len(), state.min_match);
+        for &i in &cells {
+            state.grid[i] = Some(0x11);
+        }
+
+        assert_eq!(state.resolve_matches(), 1);
+        assert_eq!(state.combo, 1);
+        for &i in &cells {
+            assert_eq!(state.grid[i], None);
+        }
+
+        //A sub-threshold run is left untouched.
+        state.grid = [None; GRID_LENGTH];
+        state.grid[start] = Some(0x11);
+        assert_eq!(state.resolve_matches(), 0);
+        assert_eq!(state.grid[start], Some(0x11));
+    }
+
+    #[test]
+    fn settle_clears_falls_refills_and_fills_the_board() {
+        let mut state = GameState::new([0; 16]);
+        state.grid = [None; GRID_LENGTH];
+
+        //Seed a clearable group and one loose piece above it, then let the whole
+        //clear -> gravity -> refill -> cascade chain run to rest.
+        let start = xy_to_i(10, 10);
+        let mut cells = vec![start];
+        for neighbour in neighbours(start).into_iter().flatten() {
+            if cells.len() < state.min_match {
+                cells.push(neighbour);
+            }
+        }
+        for &i in &cells {
+            state.grid[i] = Some(0x22);
+        }
+        state.grid[xy_to_i(5, 5)] = Some(0x13);
+
+        state.settle();
+        run_to_rest(&mut state);
+
+        //The chain always terminates with a completely full board.
+        assert!(state.grid.iter().all(|cell| cell.is_some()));
+        //Having come to rest the combo is cleared again.
+        assert_eq!(state.combo, 0);
+    }
+
+    #[test]
+    fn auto_repeat_honours_delay_then_interval() {
+        let mut state = GameState::new([0; 16]);
+        let left = Button::Left;
+        let empty = Button::Ty::empty();
+
+        //Rising edge fires immediately and arms the initial delay.
+        let pressed = InputState {
+            gamepad: left,
+            previous: empty,
+        };
+        assert!(state.repeat(&pressed, left, Dir::Left));
+
+        let held = InputState {
+            gamepad: left,
+            previous: left,
+        };
+
+        //Held but still inside the delay window: no fire.
+        state.frame_counter = REPEAT_DELAY - 1;
+        assert!(!state.repeat(&held, left, Dir::Left));
+
+        //Delay elapsed: fire, then arm the repeat interval.
+        state.frame_counter = REPEAT_DELAY;
+        assert!(state.repeat(&held, left, Dir::Left));
+
+        //Inside the interval: no fire.
+        state.frame_counter = REPEAT_DELAY + REPEAT_INTERVAL - 1;
+        assert!(!state.repeat(&held, left, Dir::Left));
+
+        //Interval elapsed: fire again.
+        state.frame_counter = REPEAT_DELAY + REPEAT_INTERVAL;
+        assert!(state.repeat(&held, left, Dir::Left));
+    }
+}